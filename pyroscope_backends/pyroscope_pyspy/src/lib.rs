@@ -12,6 +12,27 @@ use std::{
     thread::JoinHandle,
 };
 
+/// Selects whether samples are attributed by CPU-time or wall-clock
+/// presence.
+///
+/// `PprofConfig` (the in-process Rust backend, in the separate
+/// `pyroscope_pprofrs` crate) doesn't expose an equivalent option yet -
+/// this lives on `PyspyConfig` only for now.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeMode {
+    /// Attribute every sample regardless of whether the thread was on-CPU.
+    WallClock,
+    /// Only attribute samples taken while the thread was on-CPU (isn't
+    /// idle). GIL ownership is only checked when `gil_only` is set.
+    CpuTime,
+}
+
+impl Default for TimeMode {
+    fn default() -> Self {
+        TimeMode::WallClock
+    }
+}
+
 /// Pyspy Configuration
 #[derive(Debug, Clone)]
 pub struct PyspyConfig {
@@ -31,6 +52,8 @@ pub struct PyspyConfig {
     gil_only: bool,
     /// todo
     native: bool,
+    /// Attribute samples by CPU-time or wall-clock presence
+    time_mode: TimeMode,
 }
 
 impl Default for PyspyConfig {
@@ -44,6 +67,7 @@ impl Default for PyspyConfig {
             include_idle: false,
             gil_only: false,
             native: false,
+            time_mode: TimeMode::default(),
         }
     }
 }
@@ -105,6 +129,11 @@ impl PyspyConfig {
     pub fn native(self, native: bool) -> Self {
         PyspyConfig { native, ..self }
     }
+
+    /// Set the time mode (CPU-time vs wall-clock)
+    pub fn time_mode(self, time_mode: TimeMode) -> Self {
+        PyspyConfig { time_mode, ..self }
+    }
 }
 
 /// Pyspy Backend
@@ -207,6 +236,7 @@ impl Backend for Pyspy {
         let buffer = self.buffer.clone();
 
         let config = self.sampler_config.clone().unwrap();
+        let time_mode = self.config.time_mode;
 
         self.sampler_thread = Some(std::thread::spawn(move || {
             let sampler = Sampler::new(config.pid.unwrap(), &config)
@@ -214,9 +244,17 @@ impl Backend for Pyspy {
 
             let isampler = sampler.take_while(|_x| running.load(Ordering::Relaxed));
 
+            // `WallClock` attributes every sample regardless of on-CPU
+            // status, so it implies `include_idle` even if the config
+            // left it at the default `false` - otherwise the idle filter
+            // below would drop idle traces before `time_mode` is ever
+            // consulted, and `WallClock` would silently behave like
+            // `CpuTime`.
+            let keep_idle_traces = config.include_idle || time_mode == TimeMode::WallClock;
+
             for sample in isampler {
                 for trace in sample.traces {
-                    if !(config.include_idle || trace.active) {
+                    if !(keep_idle_traces || trace.active) {
                         continue;
                     }
 
@@ -224,6 +262,13 @@ impl Backend for Pyspy {
                         continue;
                     }
 
+                    // CpuTime mode only attributes time the thread was
+                    // actually scheduled on a CPU; WallClock keeps every
+                    // sample that passed the filters above.
+                    if time_mode == TimeMode::CpuTime && !trace.active {
+                        continue;
+                    }
+
                     let own_trace: StackTrace =
                         Into::<StackTraceWrapper>::into(trace.clone()).into();
 
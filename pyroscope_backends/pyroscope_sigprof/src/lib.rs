@@ -0,0 +1,645 @@
+//! A self-contained, signal-driven in-process sampler for native Rust
+//! threads.
+//!
+//! Unlike the `pyspy`/`rbspy` backends, which read another process's
+//! memory through `/proc`, this backend profiles threads of the *current*
+//! process: a POSIX per-thread interval timer (`timer_create` with
+//! `SIGEV_THREAD_ID`) delivers `SIGPROF` to each tracked thread at the
+//! configured sample rate, a minimal async-signal-safe handler records raw
+//! instruction pointers, and a collector thread symbolizes them off the
+//! hot path.
+
+use pyroscope::{
+    backend::{Backend, Report, StackFrame, StackTrace, State},
+    error::{PyroscopeError, Result},
+};
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicI32, AtomicUsize, Ordering},
+        Arc, Mutex, OnceLock,
+    },
+    thread::JoinHandle,
+    time::Duration,
+};
+
+/// Number of raw instruction pointers captured per sample. The handler
+/// stops walking frame pointers once this is full - deep recursion is
+/// truncated rather than allocating a bigger buffer in-handler.
+const MAX_FRAMES: usize = 128;
+
+/// Upper bound on how many threads can be tracked at once. Backs a static
+/// slot table (see `SLOT_TABLE`) rather than a growable collection, so
+/// that claiming a slot never allocates or blocks.
+const MAX_TRACKED_THREADS: usize = 1024;
+
+/// Selects whether samples are attributed by CPU-time or wall-clock
+/// presence, mirroring `pyspy`'s `TimeMode`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeMode {
+    /// Arm each tracked thread's timer on `CLOCK_REALTIME`: every thread is
+    /// sampled whether or not it is actually running.
+    WallClock,
+    /// Arm each tracked thread's timer on `CLOCK_THREAD_CPUTIME_ID`: only
+    /// on-CPU time accrues samples.
+    CpuTime,
+}
+
+impl Default for TimeMode {
+    fn default() -> Self {
+        TimeMode::CpuTime
+    }
+}
+
+/// Sigprof Configuration
+#[derive(Debug, Clone, Copy)]
+pub struct SigprofConfig {
+    /// Sampling rate, in Hz
+    sample_rate: u32,
+    /// CPU-time vs wall-clock sampling
+    time_mode: TimeMode,
+    /// Run a watcher thread from `start()` that installs a timer on every
+    /// thread of the process not yet tracked, including threads spawned
+    /// after `start()`. When off, only the thread that called
+    /// `initialize()` and threads registered via `track_current_thread`
+    /// are sampled.
+    track_new_threads: bool,
+}
+
+impl Default for SigprofConfig {
+    fn default() -> Self {
+        SigprofConfig {
+            sample_rate: 100,
+            time_mode: TimeMode::default(),
+            track_new_threads: true,
+        }
+    }
+}
+
+impl SigprofConfig {
+    /// Create a new SigprofConfig
+    pub fn new(sample_rate: u32) -> Self {
+        SigprofConfig {
+            sample_rate,
+            ..Default::default()
+        }
+    }
+
+    /// Set the time mode (CPU-time vs wall-clock)
+    pub fn time_mode(self, time_mode: TimeMode) -> Self {
+        SigprofConfig { time_mode, ..self }
+    }
+
+    /// Set whether threads spawned after `start()` are tracked
+    pub fn track_new_threads(self, track_new_threads: bool) -> Self {
+        SigprofConfig {
+            track_new_threads,
+            ..self
+        }
+    }
+}
+
+/// One preallocated capture slot per tracked thread. The `SIGPROF` handler
+/// only ever touches its own thread's slot, so writes never race; `ready`
+/// is the handoff flag the collector polls.
+///
+/// Lives in the fixed-size `SLOT_TABLE` rather than behind an `Arc` in a
+/// `Mutex`-guarded `Vec`: claiming a slot (see `claim_slot`) is a lock-free
+/// compare-and-swap on `tid`, not a mutex lock. That matters because
+/// `register_current_thread` can run from `register_signal_handler` - if
+/// the watcher thread's `tgkill` ever landed on a thread partway through
+/// its *own* call to `register_current_thread` (e.g. via
+/// `track_current_thread`), a mutex-based registry would have that thread
+/// try to re-lock a mutex it's already holding and deadlock itself. A
+/// lock-free table has no lock to re-enter.
+struct ThreadSlot {
+    /// Tid owning this slot, or `0` if unclaimed. Claimed with a single
+    /// `compare_exchange` from `claim_slot`.
+    tid: AtomicI32,
+    ips: [AtomicUsize; MAX_FRAMES],
+    len: AtomicUsize,
+    ready: AtomicBool,
+}
+
+impl ThreadSlot {
+    const UNCLAIMED: ThreadSlot = ThreadSlot {
+        tid: AtomicI32::new(0),
+        ips: [const { AtomicUsize::new(0) }; MAX_FRAMES],
+        len: AtomicUsize::new(0),
+        ready: AtomicBool::new(false),
+    };
+}
+
+/// Fixed-capacity, statically allocated table of capture slots. See
+/// `ThreadSlot` for why this is a plain array rather than a `Mutex<Vec<_>>`.
+static SLOT_TABLE: [ThreadSlot; MAX_TRACKED_THREADS] = [ThreadSlot::UNCLAIMED; MAX_TRACKED_THREADS];
+
+/// Claim a free slot for `tid` with a single atomic compare-and-swap - no
+/// allocation, no locking, safe to call from a signal handler. Returns
+/// `None` if every slot is already claimed.
+fn claim_slot(tid: libc::pid_t) -> Option<&'static ThreadSlot> {
+    SLOT_TABLE
+        .iter()
+        .find(|slot| slot.tid.compare_exchange(0, tid, Ordering::AcqRel, Ordering::Acquire).is_ok())
+}
+
+/// Whether `tid` already owns a slot.
+fn is_registered(tid: libc::pid_t) -> bool {
+    SLOT_TABLE.iter().any(|slot| slot.tid.load(Ordering::Acquire) == tid)
+}
+
+thread_local! {
+    /// Raw pointer to this thread's `ThreadSlot`, set once when the thread
+    /// is registered. The signal handler only ever dereferences this from
+    /// the thread that owns it, so no locking is needed in-handler.
+    static CURRENT_SLOT: std::cell::Cell<*const ThreadSlot> = const { std::cell::Cell::new(std::ptr::null()) };
+}
+
+/// `SIGPROF` handler.
+///
+/// Must be async-signal-safe: no allocation, no locking. `backtrace::trace`
+/// is deliberately *not* used here - its default unwind backends can take
+/// the dynamic linker's `dl_iterate_phdr` lock or allocate, so a thread
+/// interrupted while it happens to hold that lock would deadlock the
+/// process the moment this handler (or anything it calls) tried to take it
+/// again. Instead this walks the interrupted context's own frame-pointer
+/// chain by hand: pure reads of already-mapped stack memory, into the
+/// thread's preallocated slot.
+///
+/// Installed with `SA_SIGINFO`, so the kernel hands us the interrupted
+/// register state in `ctx` (a `ucontext_t`) - that's what lets us find the
+/// starting instruction pointer and frame pointer at all, since the
+/// registers of *this* function's own frame are not the registers that
+/// were running when `SIGPROF` arrived.
+extern "C" fn sigprof_handler(_signum: libc::c_int, _info: *mut libc::siginfo_t, ctx: *mut libc::c_void) {
+    CURRENT_SLOT.with(|cell| {
+        let slot_ptr = cell.get();
+        if slot_ptr.is_null() {
+            return;
+        }
+
+        // Safety: the pointee outlives the thread (kept alive by the
+        // static slot table) and is only ever written from this thread.
+        let slot = unsafe { &*slot_ptr };
+
+        if slot.ready.load(Ordering::Acquire) {
+            // Collector hasn't drained the previous sample yet; skip this
+            // tick rather than clobbering it.
+            return;
+        }
+
+        let mut count = 0usize;
+        // Safety: `ctx` is the `ucontext_t *` the kernel passed to this
+        // `SA_SIGINFO` handler.
+        unsafe {
+            walk_frame_pointers(ctx as *const libc::ucontext_t, |ip| {
+                if count >= MAX_FRAMES {
+                    return false;
+                }
+                slot.ips[count].store(ip, Ordering::Relaxed);
+                count += 1;
+                true
+            });
+        }
+
+        slot.len.store(count, Ordering::Relaxed);
+        slot.ready.store(true, Ordering::Release);
+    });
+}
+
+/// Async-signal-safe frame-pointer walk of the context the kernel
+/// interrupted (`ctx`, from a `SA_SIGINFO` handler), calling `on_frame`
+/// with each return address starting from the interrupted instruction
+/// pointer itself. Stops when `on_frame` returns `false`, the chain runs
+/// out, or after `MAX_FRAMES` frames.
+///
+/// Requires frame pointers to not be omitted (`-C force-frame-pointers`,
+/// which is Rust's debug-build default) - the standard limitation of any
+/// frame-pointer-based sampling profiler. Architectures other than
+/// `x86_64`/`aarch64` only get the single interrupted-instruction frame.
+unsafe fn walk_frame_pointers(ctx: *const libc::ucontext_t, mut on_frame: impl FnMut(usize) -> bool) {
+    #[cfg(target_arch = "x86_64")]
+    let (mut ip, mut fp) = {
+        let gregs = (*ctx).uc_mcontext.gregs;
+        (gregs[libc::REG_RIP as usize] as usize, gregs[libc::REG_RBP as usize] as usize)
+    };
+    #[cfg(target_arch = "aarch64")]
+    let (mut ip, mut fp) = ((*ctx).uc_mcontext.pc as usize, (*ctx).uc_mcontext.regs[29] as usize);
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    let (mut ip, mut fp) = {
+        let _ = ctx;
+        (0usize, 0usize)
+    };
+
+    if ip != 0 && !on_frame(ip) {
+        return;
+    }
+
+    let word = std::mem::size_of::<usize>();
+    for _ in 0..MAX_FRAMES {
+        if fp == 0 || fp % word != 0 {
+            break;
+        }
+
+        // Safety: `fp` is either the interrupted frame pointer or a value
+        // read from the previous frame's saved-fp slot. The zero,
+        // alignment and non-advancing checks stop the walk on most broken
+        // chains, but they don't check that `fp` points into mapped stack
+        // memory: a corrupt chain that passes them (e.g. frame pointers
+        // omitted) is dereferenced as-is, hence the frame-pointer
+        // requirement above.
+        let next_fp = *(fp as *const usize);
+        let return_addr = *((fp + word) as *const usize);
+
+        if return_addr == 0 || next_fp <= fp {
+            break;
+        }
+
+        ip = return_addr;
+        if !on_frame(ip) {
+            break;
+        }
+        fp = next_fp;
+    }
+}
+
+/// Register the calling thread: install its `ThreadSlot`, the `SIGPROF`
+/// handler (idempotent, process-wide) and a per-thread interval timer
+/// targeting this thread via `SIGEV_THREAD_ID`.
+fn register_current_thread(sample_rate: u32, time_mode: TimeMode) -> Result<()> {
+    let tid = unsafe { libc::syscall(libc::SYS_gettid) } as libc::pid_t;
+
+    if is_registered(tid) {
+        return Ok(());
+    }
+
+    let slot = match claim_slot(tid) {
+        Some(slot) => slot,
+        None => return Err(PyroscopeError::new("Sigprof: thread slot table is full")),
+    };
+    CURRENT_SLOT.with(|cell| cell.set(slot as *const ThreadSlot));
+
+    install_signal_handler()?;
+
+    // Both modes use a `timer_create` targeting this tid with
+    // `SIGEV_THREAD_ID`, just on different clocks. `ITIMER_PROF`/
+    // `setitimer` was tried for CPU-time first, but it arms a single
+    // *process-wide* timer - in a multithreaded target, only whichever
+    // thread happens to be running when it expires ever gets sampled, so
+    // every other thread's CPU time goes unobserved. `CLOCK_THREAD_CPUTIME_ID`
+    // is per-thread, same as the wall-clock timer below.
+    let clock_id = match time_mode {
+        TimeMode::CpuTime => libc::CLOCK_THREAD_CPUTIME_ID,
+        TimeMode::WallClock => libc::CLOCK_REALTIME,
+    };
+    register_thread_id_timer(tid, sample_rate, clock_id)?;
+
+    Ok(())
+}
+
+fn install_signal_handler() -> Result<()> {
+    static INSTALLED: OnceLock<()> = OnceLock::new();
+    let mut err = None;
+    INSTALLED.get_or_init(|| {
+        let mut action: libc::sigaction = unsafe { std::mem::zeroed() };
+        action.sa_sigaction = sigprof_handler as usize;
+        // SA_SIGINFO: `sigprof_handler` needs the interrupted `ucontext_t`
+        // to start its frame-pointer walk from the right registers.
+        action.sa_flags = libc::SA_RESTART | libc::SA_SIGINFO;
+        unsafe { libc::sigemptyset(&mut action.sa_mask) };
+        if unsafe { libc::sigaction(libc::SIGPROF, &action, std::ptr::null_mut()) } != 0 {
+            err = Some(PyroscopeError::new("Sigprof: sigaction failed"));
+        }
+    });
+    match err {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+/// Arm a per-thread interval timer targeting `tid` via `SIGEV_THREAD_ID`.
+/// `clock_id` is `CLOCK_REALTIME` for wall-clock sampling or
+/// `CLOCK_THREAD_CPUTIME_ID` for CPU-time sampling. Only the latter is a
+/// per-thread clock, but the timer itself is always per-thread, so (unlike
+/// the process-wide `ITIMER_PROF`) every tracked thread gets its own
+/// independent timer regardless of which clock it's on.
+#[cfg(target_os = "linux")]
+fn register_thread_id_timer(tid: libc::pid_t, sample_rate: u32, clock_id: libc::clockid_t) -> Result<()> {
+    let mut sev: libc::sigevent = unsafe { std::mem::zeroed() };
+    sev.sigev_notify = libc::SIGEV_THREAD_ID;
+    sev.sigev_signo = libc::SIGPROF;
+    sev.sigev_notify_thread_id = tid;
+
+    let mut timer_id: libc::timer_t = std::ptr::null_mut();
+    if unsafe { libc::timer_create(clock_id, &mut sev, &mut timer_id) } != 0 {
+        return Err(PyroscopeError::new("Sigprof: timer_create failed"));
+    }
+
+    let interval_ns = 1_000_000_000i64 / sample_rate.max(1) as i64;
+    // `tv_nsec` must stay below one second, so 1 Hz becomes `tv_sec: 1`.
+    let interval = libc::timespec {
+        tv_sec: (interval_ns / 1_000_000_000) as libc::time_t,
+        tv_nsec: (interval_ns % 1_000_000_000) as _,
+    };
+    let spec = libc::itimerspec { it_interval: interval, it_value: interval };
+    if unsafe { libc::timer_settime(timer_id, 0, &spec, std::ptr::null_mut()) } != 0 {
+        return Err(PyroscopeError::new("Sigprof: timer_settime failed"));
+    }
+
+    Ok(())
+}
+
+/// Signal used to ask a thread to register itself. `SIGURG` is, like Go's
+/// scheduler preemption, essentially never used by application code and is
+/// ignored by default, so repurposing it here doesn't collide with
+/// `SIGPROF` (the sampling signal) or common signal handling in hosting
+/// applications.
+#[cfg(target_os = "linux")]
+const REGISTER_SIGNAL: libc::c_int = libc::SIGURG;
+
+/// Sample rate/time mode the register-on-signal handler installs new
+/// threads with, set once when `track_new_threads` first spawns its
+/// watcher thread.
+#[cfg(target_os = "linux")]
+static TRACK_NEW_THREADS_PARAMS: OnceLock<(u32, TimeMode)> = OnceLock::new();
+
+/// Handler for `REGISTER_SIGNAL`: runs on whatever thread the watcher just
+/// signalled and registers it, installing its `SIGPROF` timer.
+///
+/// `register_current_thread` claims its slot with a lock-free CAS (see
+/// `claim_slot`) rather than a mutex, specifically so this handler is safe
+/// even in the unlikely case that a thread is signalled while it's already
+/// partway through registering itself on its own (e.g. via
+/// `track_current_thread`) - there's no lock for that reentrant call to
+/// deadlock on. `install_signal_handler`'s one-time `sigaction` call is the
+/// one part of registration that isn't itself reentrant-safe, but it's
+/// guaranteed to have already run by the time any watcher thread exists
+/// (`initialize()` always registers the calling thread, and therefore
+/// calls it, before `start()` can spawn the watcher).
+#[cfg(target_os = "linux")]
+extern "C" fn register_signal_handler(_signum: libc::c_int) {
+    if let Some(&(sample_rate, time_mode)) = TRACK_NEW_THREADS_PARAMS.get() {
+        let _ = register_current_thread(sample_rate, time_mode);
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn install_register_signal_handler() -> Result<()> {
+    static INSTALLED: OnceLock<()> = OnceLock::new();
+    let mut err = None;
+    INSTALLED.get_or_init(|| {
+        let mut action: libc::sigaction = unsafe { std::mem::zeroed() };
+        action.sa_sigaction = register_signal_handler as usize;
+        action.sa_flags = libc::SA_RESTART;
+        unsafe { libc::sigemptyset(&mut action.sa_mask) };
+        if unsafe { libc::sigaction(REGISTER_SIGNAL, &action, std::ptr::null_mut()) } != 0 {
+            err = Some(PyroscopeError::new("Sigprof: sigaction failed for register signal"));
+        }
+    });
+    match err {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+/// Ask `tid` to register itself for sampling by delivering
+/// `REGISTER_SIGNAL` to it. `register_current_thread` is a no-op if the
+/// thread is already registered, so re-signalling a thread that's still
+/// being processed is harmless.
+#[cfg(target_os = "linux")]
+fn signal_thread_to_register(tid: libc::pid_t) {
+    unsafe { libc::syscall(libc::SYS_tgkill, std::process::id() as libc::pid_t, tid, REGISTER_SIGNAL) };
+}
+
+/// Enumerate this process's live thread ids by reading `/proc/self/task`.
+/// Used to pick up threads spawned after `start()` when
+/// `track_new_threads` is enabled.
+#[cfg(target_os = "linux")]
+fn list_thread_ids() -> Vec<libc::pid_t> {
+    std::fs::read_dir("/proc/self/task")
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok())
+                .filter_map(|e| e.file_name().to_str().and_then(|s| s.parse().ok()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Sigprof Backend
+pub struct Sigprof {
+    /// Sigprof State
+    state: State,
+    /// Profiling buffer
+    buffer: Arc<Mutex<Report>>,
+    /// Sigprof Configuration
+    config: SigprofConfig,
+    /// Atomic flag to stop the collector/watcher threads
+    running: Arc<AtomicBool>,
+    /// Drains captured instruction pointers and symbolizes them
+    collector_thread: Option<JoinHandle<Result<()>>>,
+    /// Polls for newly spawned threads when `track_new_threads` is set
+    watcher_thread: Option<JoinHandle<Result<()>>>,
+}
+
+impl std::fmt::Debug for Sigprof {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Sigprof Backend")
+    }
+}
+
+impl Default for Sigprof {
+    fn default() -> Self {
+        Sigprof::new(SigprofConfig::default())
+    }
+}
+
+impl Sigprof {
+    /// Create a new Sigprof Backend
+    pub fn new(config: SigprofConfig) -> Self {
+        Sigprof {
+            state: State::Uninitialized,
+            buffer: Arc::new(Mutex::new(Report::default())),
+            config,
+            running: Arc::new(AtomicBool::new(false)),
+            collector_thread: None,
+            watcher_thread: None,
+        }
+    }
+}
+
+impl Backend for Sigprof {
+    fn get_state(&self) -> State {
+        self.state
+    }
+
+    fn spy_name(&self) -> Result<String> {
+        Ok("sigprof".to_string())
+    }
+
+    fn sample_rate(&self) -> Result<u32> {
+        Ok(self.config.sample_rate)
+    }
+
+    fn initialize(&mut self) -> Result<()> {
+        if self.state != State::Uninitialized {
+            return Err(PyroscopeError::new("Sigprof: Backend is already Initialized"));
+        }
+
+        // Track whatever thread calls initialize (usually the main thread).
+        register_current_thread(self.config.sample_rate, self.config.time_mode)?;
+
+        self.state = State::Ready;
+
+        Ok(())
+    }
+
+    fn start(&mut self) -> Result<()> {
+        if self.state != State::Ready {
+            return Err(PyroscopeError::new("Sigprof: Backend is not Ready"));
+        }
+
+        self.running.store(true, Ordering::Relaxed);
+
+        let running = Arc::clone(&self.running);
+        let buffer = self.buffer.clone();
+        self.collector_thread = Some(std::thread::spawn(move || {
+            while running.load(Ordering::Relaxed) {
+                for slot in SLOT_TABLE.iter() {
+                    if slot.tid.load(Ordering::Acquire) == 0 || !slot.ready.load(Ordering::Acquire) {
+                        continue;
+                    }
+
+                    let len = slot.len.load(Ordering::Relaxed);
+                    let frames: Vec<StackFrame> = (0..len)
+                        .filter_map(|i| {
+                            let ip = slot.ips[i].load(Ordering::Relaxed) as *mut libc::c_void;
+                            symbolize(ip)
+                        })
+                        .collect();
+
+                    slot.ready.store(false, Ordering::Release);
+
+                    if frames.is_empty() {
+                        continue;
+                    }
+
+                    let trace = StackTrace {
+                        pid: Some(std::process::id()),
+                        thread_id: Some(slot.tid.load(Ordering::Relaxed) as u64),
+                        thread_name: None,
+                        frames,
+                    };
+
+                    buffer.lock()?.record(trace)?;
+                }
+
+                std::thread::sleep(Duration::from_millis(10));
+            }
+
+            Ok(())
+        }));
+
+        if self.config.track_new_threads {
+            #[cfg(target_os = "linux")]
+            {
+                let _ = TRACK_NEW_THREADS_PARAMS.set((self.config.sample_rate, self.config.time_mode));
+                install_register_signal_handler()?;
+            }
+
+            let running = Arc::clone(&self.running);
+            self.watcher_thread = Some(std::thread::spawn(move || {
+                while running.load(Ordering::Relaxed) {
+                    // `register_current_thread` can only install a timer
+                    // on the calling thread, so this watcher can't
+                    // register a new thread directly - instead it signals
+                    // the thread with `REGISTER_SIGNAL`, whose handler
+                    // (running on that thread) does the registration.
+                    #[cfg(target_os = "linux")]
+                    for tid in list_thread_ids() {
+                        if !is_registered(tid) {
+                            signal_thread_to_register(tid);
+                        }
+                    }
+
+                    std::thread::sleep(Duration::from_millis(100));
+                }
+
+                Ok(())
+            }));
+        }
+
+        self.state = State::Running;
+
+        Ok(())
+    }
+
+    fn stop(&mut self) -> Result<()> {
+        if self.state != State::Running {
+            return Err(PyroscopeError::new("Sigprof: Backend is not Running"));
+        }
+
+        self.running.store(false, Ordering::Relaxed);
+
+        if let Some(handle) = self.collector_thread.take() {
+            handle.join().unwrap()?;
+        }
+        if let Some(handle) = self.watcher_thread.take() {
+            handle.join().unwrap()?;
+        }
+
+        self.state = State::Ready;
+
+        Ok(())
+    }
+
+    fn report(&mut self) -> Result<Vec<u8>> {
+        if self.state != State::Running {
+            return Err(PyroscopeError::new("Sigprof: Backend is not Running"));
+        }
+
+        let buffer = self.buffer.clone();
+
+        let v8: Vec<u8> = buffer.lock()?.to_string().into_bytes();
+
+        buffer.lock()?.clear();
+
+        Ok(v8)
+    }
+}
+
+/// Symbolize a single instruction pointer off the signal-handling path.
+/// `backtrace::resolve` may allocate, which is exactly why it only ever
+/// runs here, in the collector thread, and never in `sigprof_handler`.
+fn symbolize(ip: *mut libc::c_void) -> Option<StackFrame> {
+    let mut frame = None;
+    backtrace::resolve(ip, |symbol| {
+        if frame.is_some() {
+            return;
+        }
+        frame = Some(StackFrame {
+            module: None,
+            name: symbol.name().map(|n| n.to_string()),
+            filename: symbol
+                .filename()
+                .and_then(|p| p.file_name())
+                .and_then(|n| n.to_str())
+                .map(|s| s.to_string()),
+            relative_path: None,
+            absolute_path: symbol.filename().and_then(|p| p.to_str()).map(|s| s.to_string()),
+            line: symbol.lineno(),
+        });
+    });
+    frame
+}
+
+/// Explicitly register the calling thread for sampling.
+///
+/// `initialize()` only tracks the thread that calls it (typically main).
+/// With `track_new_threads` enabled (Linux only), the watcher thread picks
+/// up and registers other new threads on its own via `REGISTER_SIGNAL`, so
+/// this is only needed for manual registration - before `start()` has had
+/// a chance to notice a thread, or on platforms without the watcher.
+pub fn track_current_thread(config: &SigprofConfig) -> Result<()> {
+    register_current_thread(config.sample_rate, config.time_mode)
+}
@@ -1,22 +1,41 @@
 use std::{
-    sync::mpsc::{sync_channel, Receiver, SyncSender},
-    thread::{self, JoinHandle},
-    time::Duration,
+    collections::VecDeque,
     io::Write,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicI64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
 use reqwest::Url;
 use libflate::gzip::Encoder;
+use tokio::sync::{mpsc, Semaphore};
 
 use crate::{
     backend::Report,
     pyroscope::{PyroscopeConfig, Compression},
     utils::{get_time_range, merge_tags_with_app_name},
-    Result,
+    PyroscopeError, Result,
 };
 
 const LOG_TAG: &str = "Pyroscope::Session";
 
+/// Maximum number of session uploads in flight at once. Bounds how many
+/// concurrent keep-alive connections the shared `reqwest::Client` pool is
+/// asked to juggle.
+const MAX_CONCURRENT_UPLOADS: usize = 8;
+
+/// How often the SessionManager task wakes up to sweep the retry spool
+/// for sessions whose backoff has elapsed.
+const SPOOL_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How often the server/local clock skew is re-probed. The first probe
+/// fires immediately on startup (tokio's `interval` ticks once right
+/// away), then every interval after that.
+const CLOCK_SKEW_PROBE_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
 /// Session Signal
 ///
 /// This enum is used to send data to the session thread. It can also kill the session thread.
@@ -28,54 +47,183 @@ pub enum SessionSignal {
     Kill,
 }
 
-/// Manage sessions and send data to the server.
+/// Retry policy for sessions that fail to upload.
+///
+/// A failed [`Session::send`] is re-enqueued into a bounded spool and
+/// retried with exponential backoff (`base_delay * 2^attempt`, capped at
+/// `max_delay`, plus jitter in `[0, delay/2)` to avoid every agent in a
+/// fleet retrying in lockstep).
+///
+/// These knobs belong on `PyroscopeConfig` alongside `url`/`compression`,
+/// the same way `RetryConfig` is passed into `SessionManager::new` below -
+/// but `pyroscope.rs`, where `PyroscopeConfig` and `PyroscopeAgent` are
+/// defined, is not part of this chunk's file set, so that wiring can't be
+/// done from here. Whoever next touches `pyroscope.rs` should add a
+/// `retry_config`/`probe_url` (and `format`/`targets`) field there and
+/// pass them into `Session::with_clock_delta`/`SessionManager::new`
+/// instead of callers constructing `RetryConfig`/`Format`/`Target`
+/// directly.
+#[derive(Clone, Copy, Debug)]
+pub struct RetryConfig {
+    /// Delay before the first retry.
+    pub base_delay: Duration,
+    /// Upper bound on the backoff delay before jitter is added.
+    pub max_delay: Duration,
+    /// Number of retries before a session is dropped. `0` disables
+    /// retrying entirely.
+    pub max_attempts: u32,
+    /// Spool is trimmed (oldest first) once it holds more than this many
+    /// pending sessions.
+    pub max_spooled_sessions: usize,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        RetryConfig {
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60),
+            max_attempts: 5,
+            max_spooled_sessions: 64,
+        }
+    }
+}
+
+/// A Session that failed to upload and is waiting for its next retry.
 #[derive(Debug)]
+struct SpooledSession {
+    session: Session,
+    attempt: u32,
+    next_attempt_at: Instant,
+}
+
+/// Spool of sessions awaiting a retry, shared between the signal loop and
+/// the periodic sweep. A plain `std::sync::Mutex` is fine here: every
+/// critical section is a short, non-blocking `VecDeque` operation.
+type Spool = Arc<Mutex<VecDeque<SpooledSession>>>;
+
+/// Manage sessions and send data to the server.
+///
+/// Uploads run on an owned tokio runtime against a single, shared
+/// `reqwest::Client` so TLS/keep-alive connections are pooled across
+/// sessions instead of re-negotiated on every report.
 pub struct SessionManager {
-    /// The SessionManager thread.
-    pub handle: Option<JoinHandle<Result<()>>>,
-    /// Channel to send data to the SessionManager thread.
-    pub tx: SyncSender<SessionSignal>,
+    /// The tokio runtime driving uploads. Kept alive for the manager's
+    /// lifetime; dropping it would abort in-flight sends.
+    runtime: tokio::runtime::Runtime,
+    /// Channel to send data to the SessionManager.
+    pub tx: mpsc::Sender<SessionSignal>,
+    /// `server_time - local_time`, in seconds, from the most recent probe.
+    /// Callers building a `Session` pass this to
+    /// `Session::with_clock_delta` so an agent with a skewed clock doesn't
+    /// stamp its reports into the wrong server-side bucket.
+    clock_delta_secs: Arc<AtomicI64>,
+}
+
+impl std::fmt::Debug for SessionManager {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SessionManager").finish_non_exhaustive()
+    }
 }
 
 impl SessionManager {
-    /// Create a new SessionManager
-    pub fn new() -> Result<Self> {
+    /// Create a new SessionManager, retrying failed uploads per
+    /// `retry_config` and probing `probe_url` for server/local clock skew.
+    ///
+    /// No caller in this chunk's file set constructs a `SessionManager` -
+    /// that happens in `PyroscopeAgent::build` in `pyroscope.rs`, which
+    /// isn't part of this chunk - so this signature change has nothing
+    /// here left to update. Whoever wires `RetryConfig` onto
+    /// `PyroscopeConfig` (see `RetryConfig`'s doc comment) should pass
+    /// `config.retry_config` and `config.url.clone()` as `probe_url` at
+    /// that call site.
+    pub fn new(retry_config: RetryConfig, probe_url: String) -> Result<Self> {
         log::info!(target: LOG_TAG, "Creating SessionManager");
 
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| PyroscopeError::new(&format!("SessionManager: failed to start tokio runtime: {}", e)))?;
+
         // Create a channel for sending and receiving sessions
-        let (tx, rx): (SyncSender<SessionSignal>, Receiver<SessionSignal>) = sync_channel(10);
-
-        // Create a thread for the SessionManager
-        let handle = Some(thread::spawn(move || {
-            log::trace!(target: LOG_TAG, "Started");
-            while let Ok(signal) = rx.recv() {
-                match signal {
-                    SessionSignal::Session(session) => {
-                        // Send the session
-                        // Matching is done here (instead of ?) to avoid breaking
-                        // the SessionManager thread if the server is not available.
-                        match session.send() {
-                            Ok(_) => log::trace!("SessionManager - Session sent"),
-                            Err(e) => log::error!("SessionManager - Failed to send session: {}", e),
+        let (tx, mut rx) = mpsc::channel::<SessionSignal>(10);
+
+        let client = Arc::new(reqwest::Client::new());
+        let semaphore = Arc::new(Semaphore::new(MAX_CONCURRENT_UPLOADS));
+        let spool: Spool = Arc::new(Mutex::new(VecDeque::new()));
+        let clock_delta_secs = Arc::new(AtomicI64::new(0));
+
+        {
+            let client = client.clone();
+            let semaphore = semaphore.clone();
+            let spool = spool.clone();
+            runtime.spawn(async move {
+                log::trace!(target: LOG_TAG, "Started");
+                let mut sweep = tokio::time::interval(SPOOL_POLL_INTERVAL);
+
+                loop {
+                    tokio::select! {
+                        signal = rx.recv() => {
+                            match signal {
+                                Some(SessionSignal::Session(session)) => {
+                                    dispatch_session(session, client.clone(), semaphore.clone(), spool.clone(), retry_config);
+                                }
+                                Some(SessionSignal::Kill) => {
+                                    log::trace!(target: LOG_TAG, "Kill signal received");
+                                    flush_spool(&spool, &client).await;
+                                    return;
+                                }
+                                None => {
+                                    flush_spool(&spool, &client).await;
+                                    return;
+                                }
+                            }
+                        }
+                        _ = sweep.tick() => {
+                            retry_due_sessions(&client, &semaphore, &spool, &retry_config);
                         }
                     }
-                    SessionSignal::Kill => {
-                        // Kill the session manager
-                        log::trace!(target: LOG_TAG, "Kill signal received");
-                        return Ok(());
+                }
+            });
+        }
+
+        {
+            let client = client.clone();
+            let clock_delta_secs = clock_delta_secs.clone();
+            runtime.spawn(async move {
+                // `interval` fires once immediately, so this also serves as
+                // the initial probe described above.
+                let mut probe = tokio::time::interval(CLOCK_SKEW_PROBE_INTERVAL);
+                loop {
+                    probe.tick().await;
+                    match probe_clock_skew(&client, &probe_url).await {
+                        Ok(delta) => {
+                            log::trace!(target: LOG_TAG, "SessionManager - clock skew: {}s", delta);
+                            clock_delta_secs.store(delta, Ordering::Relaxed);
+                        }
+                        Err(e) => {
+                            log::error!(target: LOG_TAG, "SessionManager - failed to probe clock skew: {}", e)
+                        }
                     }
                 }
-            }
-            Ok(())
-        }));
+            });
+        }
+
+        Ok(SessionManager { runtime, tx, clock_delta_secs })
+    }
 
-        Ok(SessionManager { handle, tx })
+    /// `server_time - local_time`, in seconds, as of the most recent
+    /// clock-skew probe. Exposed so diagnostics can surface large skews.
+    pub fn clock_delta_secs(&self) -> i64 {
+        self.clock_delta_secs.load(Ordering::Relaxed)
     }
 
     /// Push a new session into the SessionManager
     pub fn push(&self, session: SessionSignal) -> Result<()> {
-        // Push the session into the SessionManager
-        self.tx.send(session)?;
+        // This is called from synchronous agent code, so block the calling
+        // thread on the send rather than requiring an `.await`.
+        self.tx
+            .blocking_send(session)
+            .map_err(|e| PyroscopeError::new(&format!("SessionManager: failed to push session: {}", e)))?;
 
         log::trace!(target: LOG_TAG, "SessionSignal pushed");
 
@@ -83,6 +231,259 @@ impl SessionManager {
     }
 }
 
+/// Split a freshly-received, possibly multi-target session into one
+/// single-target session per target and spawn each independently, so a
+/// slow or broken target doesn't block - or get retried alongside - the
+/// others.
+fn dispatch_session(
+    session: Session, client: Arc<reqwest::Client>, semaphore: Arc<Semaphore>, spool: Spool,
+    retry_config: RetryConfig,
+) {
+    for target in session.targets.clone() {
+        let per_target = session.with_only_target(target);
+        spawn_send(per_target, 0, client.clone(), semaphore.clone(), spool.clone(), retry_config);
+    }
+}
+
+/// Spawn a task that sends `session` (expected to carry a single target -
+/// see `dispatch_session`/`Session::with_only_target`) using the shared
+/// client, re-enqueuing it into the retry spool on failure.
+fn spawn_send(
+    session: Session, attempt: u32, client: Arc<reqwest::Client>, semaphore: Arc<Semaphore>, spool: Spool,
+    retry_config: RetryConfig,
+) {
+    tokio::spawn(async move {
+        let _permit = semaphore
+            .acquire_owned()
+            .await
+            .expect("SessionManager semaphore is never closed while the manager is alive");
+
+        match session.send(&client).await {
+            Ok(_) => log::trace!(target: LOG_TAG, "SessionManager - Session sent"),
+            Err(e) => {
+                log::error!(target: LOG_TAG, "SessionManager - Failed to send session: {}", e);
+                // Spool with the *next* attempt number, not the one that
+                // just failed, so `backoff_delay` actually grows and
+                // `max_attempts` is eventually reached.
+                spool_push(&spool, session, attempt + 1, &retry_config);
+            }
+        }
+    });
+}
+
+/// Re-enqueue a session that just failed, dropping the oldest spooled
+/// session(s) if the spool is over its configured cap.
+///
+/// `attempt` counts *all* completed sends so far, including the original
+/// one (see `spawn_send`'s `attempt + 1`), so a session is only dropped
+/// once it's used up the original send plus `max_attempts` retries - not
+/// `max_attempts` sends total.
+fn spool_push(spool: &Spool, session: Session, attempt: u32, retry_config: &RetryConfig) {
+    if retry_config.max_attempts == 0 || attempt > retry_config.max_attempts {
+        log::error!(
+            target: LOG_TAG,
+            "SessionManager - Dropping session {}-{} after {} attempt(s)",
+            session.from,
+            session.until,
+            attempt
+        );
+        return;
+    }
+
+    // `attempt` is 1 for the first retry, which waits `base_delay * 2^0`.
+    let next_attempt_at = Instant::now() + backoff_delay(attempt.saturating_sub(1), retry_config);
+
+    let mut spool = spool.lock().expect("SessionManager spool mutex poisoned");
+    while !spool.is_empty() && spool.len() >= retry_config.max_spooled_sessions {
+        if let Some(dropped) = spool.pop_front() {
+            log::error!(
+                target: LOG_TAG,
+                "SessionManager - Spool full, dropping oldest session {}-{}",
+                dropped.session.from,
+                dropped.session.until
+            );
+        }
+    }
+
+    spool.push_back(SpooledSession { session, attempt, next_attempt_at });
+}
+
+/// Spawn a retry for every spooled session whose backoff has elapsed.
+fn retry_due_sessions(client: &Arc<reqwest::Client>, semaphore: &Arc<Semaphore>, spool: &Spool, retry_config: &RetryConfig) {
+    let now = Instant::now();
+
+    let due: Vec<SpooledSession> = {
+        let mut guard = spool.lock().expect("SessionManager spool mutex poisoned");
+        let mut due = Vec::new();
+        let mut remaining = VecDeque::with_capacity(guard.len());
+        for spooled in guard.drain(..) {
+            if spooled.next_attempt_at <= now {
+                due.push(spooled);
+            } else {
+                remaining.push_back(spooled);
+            }
+        }
+        *guard = remaining;
+        due
+    };
+
+    for spooled in due {
+        spawn_send(
+            spooled.session,
+            spooled.attempt,
+            client.clone(),
+            semaphore.clone(),
+            spool.clone(),
+            *retry_config,
+        );
+    }
+}
+
+/// Best-effort final attempt to drain the spool before the SessionManager
+/// task exits, e.g. on `SessionSignal::Kill`. Runs sequentially, outside
+/// the concurrency cap, since the manager is shutting down anyway.
+async fn flush_spool(spool: &Spool, client: &reqwest::Client) {
+    let pending: Vec<SpooledSession> = {
+        let mut guard = spool.lock().expect("SessionManager spool mutex poisoned");
+        guard.drain(..).collect()
+    };
+
+    for spooled in pending {
+        if let Err(e) = spooled.session.send(client).await {
+            log::error!(
+                target: LOG_TAG,
+                "SessionManager - Dropping spooled session {}-{} on shutdown: {}",
+                spooled.session.from,
+                spooled.session.until,
+                e
+            );
+        }
+    }
+}
+
+/// Probe `url` for server/local clock skew by reading the `Date` header
+/// off a lightweight `HEAD` request, returning `server_time - local_time`
+/// in seconds.
+async fn probe_clock_skew(client: &reqwest::Client, url: &str) -> Result<i64> {
+    let sent_at = SystemTime::now();
+    let response = client.head(url).timeout(Duration::from_secs(5)).send().await?;
+    let received_at = SystemTime::now();
+
+    let date_header = response
+        .headers()
+        .get(reqwest::header::DATE)
+        .ok_or_else(|| PyroscopeError::new("SessionManager: probe response had no Date header"))?
+        .to_str()
+        .map_err(|e| PyroscopeError::new(&format!("SessionManager: invalid Date header: {}", e)))?;
+
+    let server_time = httpdate::parse_http_date(date_header)
+        .map_err(|e| PyroscopeError::new(&format!("SessionManager: unparseable Date header: {}", e)))?;
+
+    // Split the difference on however long the round trip took.
+    let local_mid = sent_at + received_at.duration_since(sent_at).unwrap_or_default() / 2;
+
+    Ok(match server_time.duration_since(local_mid) {
+        Ok(ahead) => ahead.as_secs() as i64,
+        Err(behind) => -(behind.duration().as_secs() as i64),
+    })
+}
+
+/// `delay = min(base * 2^attempt, cap)`, plus jitter in `[0, delay/2)`.
+fn backoff_delay(attempt: u32, retry_config: &RetryConfig) -> Duration {
+    let exp = retry_config.base_delay.saturating_mul(1u32.checked_shl(attempt).unwrap_or(u32::MAX));
+    let capped = exp.min(retry_config.max_delay);
+
+    let jitter_bound = capped / 2;
+    capped + cheap_jitter(jitter_bound)
+}
+
+/// A dependency-free source of jitter: not cryptographically random, but
+/// enough to avoid every session in a spool retrying in lockstep.
+fn cheap_jitter(bound: Duration) -> Duration {
+    if bound.is_zero() {
+        return Duration::ZERO;
+    }
+
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or(0);
+
+    let bound_nanos = bound.as_nanos().max(1);
+    Duration::from_nanos((nanos as u128 % bound_nanos) as u64)
+}
+
+/// Wire format a [`Session`] is uploaded in.
+///
+/// Belongs on `PyroscopeConfig` as a `format` field, same as `RetryConfig`
+/// (see its doc comment) - threaded through `Session` directly here
+/// because `pyroscope.rs` isn't part of this chunk's file set.
+///
+/// `Report` only exposes a folded-text representation in this chunk (see
+/// `process`'s use of `report.to_string()`), so `Pprof` is produced by
+/// converting that folded text rather than reading backend-native pprof
+/// data - every backend gets a valid pprof payload, not just ones that
+/// happen to sample with `pprof` natively.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Format {
+    /// The collapsed-stack text format the server has always accepted.
+    #[default]
+    Folded,
+    /// `pprof` protobuf, converted from the folded representation so it
+    /// can carry stack-trace structure the server's pprof ingest expects.
+    Pprof,
+}
+
+/// Where an uploaded [`Session`] is delivered.
+///
+/// A `Session` fans out to every target in `Session::targets`
+/// independently - one slow or broken target doesn't block, or get
+/// retried alongside, the others. Belongs on `PyroscopeConfig` as the
+/// list of configured targets, same as `RetryConfig` (see its doc
+/// comment) - threaded through `Session` directly here because
+/// `pyroscope.rs` isn't part of this chunk's file set.
+#[derive(Clone, Debug)]
+pub enum Target {
+    /// POST to a Pyroscope-compatible HTTP ingest endpoint.
+    Http(HttpTarget),
+    /// Write to a rotating set of local files instead of the network, so
+    /// a host with no egress can capture profiles and replay/upload them
+    /// later.
+    File(FileSinkConfig),
+}
+
+/// A single HTTP ingest endpoint, with its own auth and compression so a
+/// session can, for example, ship uncompressed to a local relay and
+/// gzip'd to a central aggregator in the same fan-out.
+#[derive(Clone, Debug)]
+pub struct HttpTarget {
+    pub url: String,
+    pub auth_token: Option<String>,
+    pub compression: Option<Compression>,
+}
+
+/// Configuration for the local-file [`Target`].
+#[derive(Clone, Debug)]
+pub struct FileSinkConfig {
+    /// Directory reports are written into. Created if missing.
+    pub dir: PathBuf,
+    /// Gzip-compress each file, same as `Compression::GZIP` does for HTTP
+    /// uploads.
+    pub gzip: bool,
+}
+
+impl FileSinkConfig {
+    /// Create a new FileSinkConfig writing into `dir`, uncompressed.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        FileSinkConfig { dir: dir.into(), gzip: false }
+    }
+
+    /// Gzip-compress each written file.
+    pub fn gzip(self, gzip: bool) -> Self {
+        FileSinkConfig { gzip, ..self }
+    }
+}
+
 /// Pyroscope Session
 ///
 /// Used to contain the session data, and send it to the server.
@@ -90,6 +491,11 @@ impl SessionManager {
 pub struct Session {
     pub config: PyroscopeConfig,
     pub reports: Vec<Report>,
+    /// Wire format reports are serialized as when uploaded.
+    pub format: Format,
+    /// Targets this session is fanned out to. Defaults to a single HTTP
+    /// target built from `config.url`/`auth_token`/`compression`.
+    pub targets: Vec<Target>,
     // unix time
     pub from: u64,
     // unix time
@@ -106,45 +512,98 @@ impl Session {
     /// let session = Session::new(until, config, report)?;
     /// ```
     pub fn new(until: u64, config: PyroscopeConfig, reports: Vec<Report>) -> Result<Self> {
+        Session::with_clock_delta(until, config, reports, 0)
+    }
+
+    /// Create a new Session, shifting `from`/`until` by `clock_delta_secs`
+    /// (`server_time - local_time`, as reported by
+    /// `SessionManager::clock_delta_secs`) so a host with a skewed clock
+    /// still stamps its reports into the server's expected time window.
+    pub fn with_clock_delta(
+        until: u64, config: PyroscopeConfig, reports: Vec<Report>, clock_delta_secs: i64,
+    ) -> Result<Self> {
         log::info!(target: LOG_TAG, "Creating Session");
 
         // get_time_range should be used with "from". We balance this by reducing
         // 10s from the returned range.
         let time_range = get_time_range(until)?;
+        let shift = |t: u64| -> u64 { (t as i64 + clock_delta_secs).max(0) as u64 };
+
+        let default_target = Target::Http(HttpTarget {
+            url: config.url.clone(),
+            auth_token: config.auth_token.clone(),
+            compression: config.compression.clone(),
+        });
 
         Ok(Self {
             config,
             reports,
-            from: time_range.from - 10,
-            until: time_range.until - 10,
+            format: Format::default(),
+            targets: vec![default_target],
+            from: shift(time_range.from - 10),
+            until: shift(time_range.until - 10),
         })
     }
 
-    /// Send the session to the server and consumes the session object.
+    /// Set the wire format reports are uploaded in.
+    pub fn format(self, format: Format) -> Self {
+        Self { format, ..self }
+    }
+
+    /// Replace the single default target with `target`.
+    pub fn target(self, target: Target) -> Self {
+        Self { targets: vec![target], ..self }
+    }
+
+    /// Fan out to every target in `targets` instead of the single default.
+    pub fn targets(self, targets: Vec<Target>) -> Self {
+        Self { targets, ..self }
+    }
+
+    /// Add another target to fan out to, alongside the existing ones.
+    pub fn add_target(mut self, target: Target) -> Self {
+        self.targets.push(target);
+        self
+    }
+
+    /// A copy of this session that only fans out to `target`, used to
+    /// retry a single failed target without resending to the others.
+    fn with_only_target(&self, target: Target) -> Self {
+        Self { targets: vec![target], ..self.clone() }
+    }
+
+    /// Send the session to the server.
+    ///
+    /// Targets are sent in order and the first failure short-circuits the
+    /// rest; `SessionManager` gets true per-target independence by
+    /// splitting a multi-target session into one single-target session
+    /// per target (see `with_only_target`) before calling `send` on each.
     /// # Example
     /// ```ignore
     /// let config = PyroscopeConfig::new("https://localhost:8080", "my-app");
     /// let report = vec![1, 2, 3];
     /// let until = 154065120;
     /// let session = Session::new(until, config, report)?;
-    /// session.send()?;
+    /// session.send(&client).await?;
     /// ```
-    pub fn send(self) -> Result<()> {
+    pub async fn send(&self, client: &reqwest::Client) -> Result<()> {
         // Check if the report is empty
         if self.reports.is_empty() {
             return Ok(());
         }
 
-        // Loop through the reports and process them
-        for report in &self.reports {
-            self.process(report)?;
+        // Loop through the reports and process them. The index is passed
+        // through to `write_to_file` so multiple reports in one session
+        // (e.g. more than one backend) don't collide on the same path.
+        for (index, report) in self.reports.iter().enumerate() {
+            self.process(client, index, report).await?;
         }
 
         Ok(())
     }
 
     /// Process a report and send it to the server.
-    fn process(&self, report: &Report) -> Result<()> {
+    async fn process(&self, client: &reqwest::Client, report_index: usize, report: &Report) -> Result<()> {
         log::info!(
             target: LOG_TAG,
             "Sending Session: {} - {}",
@@ -152,66 +611,253 @@ impl Session {
             self.until
         );
 
-        // Convert a report to a byte array
-        let report_u8 = report.to_string().into_bytes();
+        // Convert a report to a byte array, in the wire format this
+        // session was configured for.
+        let (report_u8, format_param, content_type) = match self.format {
+            Format::Folded => (report.to_string().into_bytes(), "folded", "binary/octet-stream"),
+            Format::Pprof => (folded_to_pprof(&report.to_string()), "pprof", "application/octet-stream"),
+        };
 
         // Check if the report is empty
         if report_u8.is_empty() {
             return Ok(());
         }
 
-        // Create a new client
-        let client = reqwest::blocking::Client::new();
-
-        // Clone URL
-        let url = self.config.url.clone();
-
         // Merge application name with Tags
         let application_name = merge_tags_with_app_name(
             self.config.application_name.clone(),
             report.metadata.tags.clone().into_iter().collect(),
         )?;
 
+        // Every target gets its own copy of the encoded report. The first
+        // failing target returns early; `SessionManager` retries targets
+        // independently by giving each its own session, see
+        // `Session::with_only_target`.
+        for target in &self.targets {
+            match target {
+                Target::Http(http_target) => {
+                    self.upload(client, http_target, report_u8.clone(), format_param, content_type, &application_name)
+                        .await?
+                }
+                Target::File(sink) => self.write_to_file(sink, report_index, report_u8.clone(), format_param)?,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// POST a processed report to a single HTTP ingest endpoint.
+    async fn upload(
+        &self, client: &reqwest::Client, target: &HttpTarget, report_u8: Vec<u8>, format_param: &str,
+        content_type: &str, application_name: &str,
+    ) -> Result<()> {
         // Parse URL
-        let parsed_url = Url::parse(&url)?;
+        let parsed_url = Url::parse(&target.url)?;
         let joined = parsed_url.join("ingest")?;
 
         // Create Reqwest builder
-        let mut req_builder = client
-            .post(joined.as_str())
-            .header("Content-Type", "binary/octet-stream");
+        let mut req_builder = client.post(joined.as_str()).header("Content-Type", content_type);
 
         // Set authentication token
-        //if self.config.auth_token.is_some() {
-        //req_builder = req_builder.bearer_auth(self.config.auth_token.clone().unwrap());
-        //}
-        // rewrite with let some
-        if let Some(auth_token) = self.config.auth_token.clone() {
+        if let Some(auth_token) = target.auth_token.clone() {
             req_builder = req_builder.bearer_auth(auth_token);
         }
-        let body = match &self.config.compression {
+        let body = match &target.compression {
             None => report_u8,
             Some(Compression::GZIP) => {
                 req_builder = req_builder.header("Content-encoding", "gzip");
-                let mut encoder = Encoder::new(Vec::new()).unwrap();
-                encoder.write_all(&report_u8).unwrap();
-                encoder.finish().into_result().unwrap()
+                gzip(&report_u8)
             }
         };
 
         // Send the request
         req_builder
             .query(&[
-                ("name", application_name.as_str()),
+                ("name", application_name),
                 ("from", &format!("{}", self.from)),
                 ("until", &format!("{}", self.until)),
-                ("format", "folded"),
+                ("format", format_param),
                 ("sampleRate", &format!("{}", self.config.sample_rate)),
                 ("spyName", self.config.spy_name.as_str()),
             ])
             .body(body)
             .timeout(Duration::from_secs(10))
-            .send()?;
+            .send()
+            .await?;
         Ok(())
     }
+
+    /// Write a processed report to the local-file sink instead of
+    /// uploading it. Files are named by their time window, plus the
+    /// report's index within this session, so a later replay tool can
+    /// sort and re-upload them in order - and so a session carrying more
+    /// than one report (e.g. two backends) doesn't have one silently
+    /// overwrite the other on disk.
+    fn write_to_file(&self, sink: &FileSinkConfig, report_index: usize, report_u8: Vec<u8>, format_param: &str) -> Result<()> {
+        std::fs::create_dir_all(&sink.dir)?;
+
+        let body = if sink.gzip { gzip(&report_u8) } else { report_u8 };
+        let ext = if sink.gzip { format!("{}.gz", format_param) } else { format_param.to_string() };
+
+        let path = sink.dir.join(format!("{}-{}-{}.{}", self.from, self.until, report_index, ext));
+        std::fs::write(path, body)?;
+
+        Ok(())
+    }
+}
+
+/// Gzip-compress `data` with the same encoder used for HTTP uploads.
+fn gzip(data: &[u8]) -> Vec<u8> {
+    let mut encoder = Encoder::new(Vec::new()).unwrap();
+    encoder.write_all(data).unwrap();
+    encoder.finish().into_result().unwrap()
+}
+
+/// Convert a folded-stack report (`func_a;func_b;func_c count`, one stack
+/// per line) into a minimal `pprof` `Profile` message.
+///
+/// Every distinct function name gets one `Function` and one `Location`
+/// (folded text carries no address/line information to split them
+/// further); each line becomes one `Sample` whose `location_id`s run
+/// leaf-first, as pprof expects, even though folded text lists frames
+/// root-first.
+fn folded_to_pprof(folded: &str) -> Vec<u8> {
+    let mut strings: Vec<String> = vec![String::new()];
+    let mut string_ids: std::collections::HashMap<String, i64> = std::collections::HashMap::new();
+
+    let samples_type = intern(&mut strings, &mut string_ids, "samples");
+    let count_unit = intern(&mut strings, &mut string_ids, "count");
+
+    let mut function_ids: std::collections::HashMap<String, u64> = std::collections::HashMap::new();
+    let mut functions = Vec::new();
+    let mut locations = Vec::new();
+    let mut samples = Vec::new();
+
+    for line in folded.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let Some((stack, value)) = line.rsplit_once(' ') else {
+            continue;
+        };
+        let Ok(value) = value.parse::<i64>() else {
+            continue;
+        };
+
+        let mut location_ids: Vec<u64> = Vec::new();
+        for frame in stack.split(';').filter(|f| !f.is_empty()) {
+            let id = match function_ids.get(frame) {
+                Some(&id) => id,
+                None => {
+                    let id = functions.len() as u64 + 1;
+                    let name = intern(&mut strings, &mut string_ids, frame);
+                    functions.push(encode_function(id, name));
+                    locations.push(encode_location(id, id));
+                    function_ids.insert(frame.to_string(), id);
+                    id
+                }
+            };
+            location_ids.push(id);
+        }
+        // Folded stacks list root first; pprof samples list the leaf
+        // (innermost frame) first.
+        location_ids.reverse();
+
+        samples.push(encode_sample(&location_ids, value));
+    }
+
+    let sample_type = encode_value_type(samples_type, count_unit);
+
+    let mut profile = Vec::new();
+    push_bytes_field(&mut profile, 1, &sample_type);
+    for sample in &samples {
+        push_bytes_field(&mut profile, 2, sample);
+    }
+    for location in &locations {
+        push_bytes_field(&mut profile, 4, location);
+    }
+    for function in &functions {
+        push_bytes_field(&mut profile, 5, function);
+    }
+    for s in &strings {
+        push_bytes_field(&mut profile, 6, s.as_bytes());
+    }
+
+    profile
+}
+
+/// Return `s`'s index in pprof's `string_table`, interning it if this is
+/// the first time it's been seen.
+fn intern(strings: &mut Vec<String>, string_ids: &mut std::collections::HashMap<String, i64>, s: &str) -> i64 {
+    if let Some(&id) = string_ids.get(s) {
+        return id;
+    }
+    let id = strings.len() as i64;
+    strings.push(s.to_string());
+    string_ids.insert(s.to_string(), id);
+    id
+}
+
+fn encode_value_type(r#type: i64, unit: i64) -> Vec<u8> {
+    let mut buf = Vec::new();
+    push_varint_field(&mut buf, 1, r#type as u64);
+    push_varint_field(&mut buf, 2, unit as u64);
+    buf
+}
+
+fn encode_sample(location_ids: &[u64], value: i64) -> Vec<u8> {
+    let mut buf = Vec::new();
+    for id in location_ids {
+        push_varint_field(&mut buf, 1, *id);
+    }
+    push_varint_field(&mut buf, 2, value as u64);
+    buf
+}
+
+fn encode_location(id: u64, function_id: u64) -> Vec<u8> {
+    let mut line = Vec::new();
+    push_varint_field(&mut line, 1, function_id);
+
+    let mut buf = Vec::new();
+    push_varint_field(&mut buf, 1, id);
+    push_bytes_field(&mut buf, 4, &line);
+    buf
+}
+
+fn encode_function(id: u64, name: i64) -> Vec<u8> {
+    let mut buf = Vec::new();
+    push_varint_field(&mut buf, 1, id);
+    push_varint_field(&mut buf, 2, name as u64);
+    buf
+}
+
+/// Append a protobuf varint-typed field (wire type 0).
+fn push_varint_field(buf: &mut Vec<u8>, field_number: u32, value: u64) {
+    push_tag(buf, field_number, 0);
+    push_varint(buf, value);
+}
+
+/// Append a protobuf length-delimited field (wire type 2): a string, bytes
+/// blob, or nested message.
+fn push_bytes_field(buf: &mut Vec<u8>, field_number: u32, value: &[u8]) {
+    push_tag(buf, field_number, 2);
+    push_varint(buf, value.len() as u64);
+    buf.extend_from_slice(value);
+}
+
+fn push_tag(buf: &mut Vec<u8>, field_number: u32, wire_type: u8) {
+    push_varint(buf, ((field_number as u64) << 3) | wire_type as u64);
+}
+
+fn push_varint(buf: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            buf.push(byte);
+            break;
+        }
+        buf.push(byte | 0x80);
+    }
 }
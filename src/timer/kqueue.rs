@@ -0,0 +1,69 @@
+// Copyright 2021 Developers of Pyroscope.
+
+// Licensed under the Apache License, Version 2.0 <LICENSE or
+// https://www.apache.org/licenses/LICENSE-2.0>. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use crate::utils::check_err;
+use crate::Result;
+
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::time::Duration;
+
+use super::TimerImpl;
+
+/// macOS/BSD timer backend, built on `kqueue`'s `EVFILT_TIMER`.
+///
+/// `rustix` (used by the Linux backend, see `epoll.rs`) doesn't expose a
+/// `kqueue` wrapper, so the `kevent`/`kqueue` calls themselves stay on raw
+/// `libc`; what it *does* give us is `OwnedFd`, so the descriptor is still
+/// closed automatically on drop - including on an early `?` return from
+/// `initialize` - rather than via a manual `libc::close` in a hand-written
+/// `Drop` impl.
+#[derive(Debug)]
+pub(crate) struct KqueueTimer {
+    kq: OwnedFd,
+}
+
+impl TimerImpl for KqueueTimer {
+    fn initialize(cycle: Duration) -> Result<Self> {
+        let raw_kq = check_err(unsafe { libc::kqueue() })?;
+        // Safety: `libc::kqueue()` just returned this fd and we don't
+        // touch it again except through `kq`.
+        let kq = unsafe { OwnedFd::from_raw_fd(raw_kq) };
+
+        let mut event: libc::kevent = unsafe { std::mem::zeroed() };
+        event.ident = 1;
+        event.filter = libc::EVFILT_TIMER;
+        event.flags = libc::EV_ADD | libc::EV_ENABLE;
+
+        // NOTE_NSECONDS is only available on Darwin; the BSDs interpret
+        // `data` as milliseconds by default.
+        #[cfg(any(target_os = "macos", target_os = "ios"))]
+        {
+            event.fflags = libc::NOTE_NSECONDS;
+            event.data = cycle.as_nanos() as _;
+        }
+        #[cfg(not(any(target_os = "macos", target_os = "ios")))]
+        {
+            event.data = cycle.as_millis() as _;
+        }
+
+        check_err(unsafe {
+            libc::kevent(kq.as_raw_fd(), &event, 1, std::ptr::null_mut(), 0, std::ptr::null())
+        })?;
+
+        Ok(Self { kq })
+    }
+
+    fn wait_next(&self) -> Result<()> {
+        let mut event: libc::kevent = unsafe { std::mem::zeroed() };
+
+        // Block until the timer fires.
+        check_err(unsafe {
+            libc::kevent(self.kq.as_raw_fd(), std::ptr::null(), 0, &mut event, 1, std::ptr::null())
+        })?;
+
+        Ok(())
+    }
+}
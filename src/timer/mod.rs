@@ -0,0 +1,157 @@
+// Copyright 2021 Developers of Pyroscope.
+
+// Licensed under the Apache License, Version 2.0 <LICENSE or
+// https://www.apache.org/licenses/LICENSE-2.0>. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A thread that sends a notification every `cycle`
+//!
+//! `Timer` dispatches an event to attached listeners (mpsc::Sender) on a
+//! fixed cadence. The OS primitive used to wait for the next tick differs
+//! per platform - timerfd+epoll on Linux/Android, kqueue's `EVFILT_TIMER`
+//! on macOS/BSD, and a condvar-park fallback elsewhere - but all of them
+//! implement [`TimerImpl`], so `Timer`'s public API (`initialize`,
+//! `attach_listener`, `drop_listeners`) is identical across platforms.
+//!
+//! The Timer thread runs until `drop_listeners` clears the listeners; it
+//! then exits after its next tick. The thread is detached, not joined.
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod epoll;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use epoll::LinuxTimer as PlatformTimer;
+
+#[cfg(any(
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd",
+    target_os = "dragonfly"
+))]
+mod kqueue;
+#[cfg(any(
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd",
+    target_os = "dragonfly"
+))]
+use kqueue::KqueueTimer as PlatformTimer;
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd",
+    target_os = "dragonfly"
+)))]
+mod park;
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd",
+    target_os = "dragonfly"
+)))]
+use park::ParkTimer as PlatformTimer;
+
+use crate::pyroscope::AgentSignal;
+use crate::utils::get_time_range;
+use crate::PyroscopeError;
+use crate::Result;
+
+use std::sync::{
+    mpsc::{channel, Sender},
+    Arc, Mutex,
+};
+use std::thread;
+use std::time::Duration;
+
+/// A platform-specific backend that fires once per `cycle`.
+///
+/// Implementors are free to use whatever OS primitive is most natural on
+/// their platform (timerfd/epoll, kqueue, a waitable timer, ...); `Timer`
+/// only requires that `wait_next` blocks until the next tick fires.
+pub(crate) trait TimerImpl: Send + 'static {
+    /// Arm the timer so it fires every `cycle`.
+    fn initialize(cycle: Duration) -> Result<Self>
+    where
+        Self: Sized;
+
+    /// Block the calling thread until the next tick fires.
+    fn wait_next(&self) -> Result<()>;
+}
+
+#[derive(Debug)]
+pub struct Timer {
+    /// A vector to store listeners (mpsc::Sender)
+    txs: Arc<Mutex<Vec<Sender<AgentSignal>>>>,
+}
+
+impl Timer {
+    /// Initialize Timer and run a thread to send events to attached listeners
+    pub fn initialize(cycle: Duration) -> Result<Self> {
+        let txs = Arc::new(Mutex::new(Vec::new()));
+
+        // Add a dummy tx so the below thread does not terminate early
+        // XXX FIXME
+        let (tx, _rx) = channel();
+        txs.lock()?.push(tx);
+
+        let backend = PlatformTimer::initialize(cycle)?;
+
+        {
+            let txs = txs.clone();
+            thread::spawn(move || {
+                loop {
+                    // Exit thread if there are no listeners
+                    if txs.lock()?.is_empty() {
+                        return Ok::<_, PyroscopeError>(());
+                    }
+
+                    // Block until the platform backend fires
+                    backend.wait_next()?;
+
+                    // Get the current time range
+                    let from = AgentSignal::NextSnapshot(get_time_range(0)?.from);
+
+                    // Iterate through Senders
+                    txs.lock()?.iter().for_each(|tx| {
+                        // Send event to attached Sender
+                        if tx.send(from).is_ok() {}
+                    });
+                }
+            });
+        }
+
+        Ok(Self { txs })
+    }
+
+    /// Attach an mpsc::Sender to Timer
+    ///
+    /// Timer will dispatch an event with the timestamp of the current instant,
+    /// every `cycle` to all attached senders
+    pub fn attach_listener(&mut self, tx: Sender<AgentSignal>) -> Result<()> {
+        // Push Sender to a Vector of Sender(s)
+        let txs = Arc::clone(&self.txs);
+        txs.lock()?.push(tx);
+
+        Ok(())
+    }
+
+    /// Clear the listeners (txs) from Timer. This will shutdown the Timer thread
+    pub fn drop_listeners(&mut self) -> Result<()> {
+        let txs = Arc::clone(&self.txs);
+        txs.lock()?.clear();
+
+        Ok(())
+    }
+}
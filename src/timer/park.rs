@@ -0,0 +1,53 @@
+// Copyright 2021 Developers of Pyroscope.
+
+// Licensed under the Apache License, Version 2.0 <LICENSE or
+// https://www.apache.org/licenses/LICENSE-2.0>. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use crate::Result;
+
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use super::TimerImpl;
+
+/// Fallback timer backend for platforms without a native timer fd (e.g.
+/// Windows, where a waitable-timer/IOCP backend is future work).
+///
+/// Sleeps in a `Condvar::wait_timeout` loop anchored to a monotonic
+/// `Instant`, so a spurious wakeup re-sleeps for the remaining time
+/// instead of drifting the cadence.
+#[derive(Debug)]
+pub(crate) struct ParkTimer {
+    cycle: Duration,
+    lock: Mutex<()>,
+    cond: Condvar,
+}
+
+impl TimerImpl for ParkTimer {
+    fn initialize(cycle: Duration) -> Result<Self> {
+        Ok(Self {
+            cycle,
+            lock: Mutex::new(()),
+            cond: Condvar::new(),
+        })
+    }
+
+    fn wait_next(&self) -> Result<()> {
+        let deadline = Instant::now() + self.cycle;
+        let mut guard = self.lock.lock()?;
+
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Ok(());
+            }
+
+            let (next_guard, timeout) = self.cond.wait_timeout(guard, remaining)?;
+            guard = next_guard;
+            if timeout.timed_out() && Instant::now() >= deadline {
+                return Ok(());
+            }
+        }
+    }
+}